@@ -0,0 +1,324 @@
+//! Assemble the flat samples produced by [`crate::line`] into complete metric families.
+//!
+//! The text format represents a histogram or summary as several independent sample lines
+//! (`X_bucket`, `X_sum`, `X_count`, or `X{quantile=...}`) that share a `TYPE` declaration and
+//! a label set (minus the `le`/`quantile` label itself). [`aggregate`] walks a stream of
+//! [`Line`]s in order and stitches those samples back into single [`Histogram`]/[`Summary`]
+//! values, passing counters, gauges, and untyped samples through unchanged.
+
+use crate::{Header, Line, Metric, MetricKind};
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// One fully assembled metric, grouped by label set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupKind {
+    Counter(Metric),
+    Gauge(Metric),
+    Untyped(Metric),
+    Histogram(Histogram),
+    Summary(Summary),
+}
+
+/// A complete histogram for a single label set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    /// `(upper bound, cumulative count)`, sorted ascending by upper bound. The `+Inf` bucket
+    /// is always present and always sorts last.
+    pub buckets: Vec<(f64, f64)>,
+    pub sum: f64,
+    pub count: f64,
+}
+
+/// A complete summary for a single label set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    /// `(quantile, value)`, sorted ascending by quantile.
+    pub quantiles: Vec<(f64, f64)>,
+    pub sum: f64,
+    pub count: f64,
+}
+
+#[derive(Debug, Default)]
+struct HistogramAcc {
+    labels: BTreeMap<String, String>,
+    buckets: Vec<(f64, f64)>,
+    sum: f64,
+    count: f64,
+}
+
+impl HistogramAcc {
+    fn finish(self, name: String) -> Histogram {
+        let mut buckets = self.buckets;
+        if !buckets.iter().any(|(le, _)| *le == f64::INFINITY) {
+            buckets.push((f64::INFINITY, self.count));
+        }
+        buckets.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        Histogram {
+            name,
+            labels: self.labels,
+            buckets,
+            sum: self.sum,
+            count: self.count,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SummaryAcc {
+    labels: BTreeMap<String, String>,
+    quantiles: Vec<(f64, f64)>,
+    sum: f64,
+    count: f64,
+}
+
+impl SummaryAcc {
+    fn finish(self, name: String) -> Summary {
+        let mut quantiles = self.quantiles;
+        quantiles.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+        Summary {
+            name,
+            labels: self.labels,
+            quantiles,
+            sum: self.sum,
+            count: self.count,
+        }
+    }
+}
+
+/// Parse a bucket upper bound or quantile, which (per the text format) may be `+Inf`/`-Inf`.
+fn parse_bound(input: &str) -> Option<f64> {
+    match input {
+        "+Inf" => Some(f64::INFINITY),
+        "-Inf" => Some(f64::NEG_INFINITY),
+        _ => input.parse().ok(),
+    }
+}
+
+/// Remove `key` from `labels`, returning the remaining labels and the removed value.
+fn split_label(labels: &BTreeMap<String, String>, key: &str) -> (BTreeMap<String, String>, Option<String>) {
+    let mut rest = labels.clone();
+    let value = rest.remove(key);
+    (rest, value)
+}
+
+fn label_key(labels: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    labels.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+/// Walk a stream of parsed [`Line`]s and group their samples into typed families.
+///
+/// Counters, gauges, and samples with no preceding `TYPE` (which default to
+/// [`MetricKind::Untyped`]) pass through as single [`GroupKind`] entries. `histogram` and
+/// `summary` families are assembled from their constituent `_bucket`/`_sum`/`_count` and
+/// `quantile` samples, grouped by every label except `le`/`quantile`.
+pub fn aggregate(lines: impl IntoIterator<Item = Line>) -> Vec<GroupKind> {
+    let mut current_kind: BTreeMap<String, MetricKind> = BTreeMap::new();
+    let mut histograms: BTreeMap<(String, Vec<(String, String)>), HistogramAcc> = BTreeMap::new();
+    let mut summaries: BTreeMap<(String, Vec<(String, String)>), SummaryAcc> = BTreeMap::new();
+    let mut passthrough = Vec::new();
+
+    for line in lines {
+        let metric = match line {
+            Line::Header(Header { metric_name, kind }) => {
+                current_kind.insert(metric_name, kind);
+                continue;
+            }
+            Line::Help(_) | Line::Unit(_) => continue,
+            Line::Metric(metric) => metric,
+        };
+
+        let bucket_base = metric
+            .name
+            .strip_suffix("_bucket")
+            .filter(|base| current_kind.get(*base) == Some(&MetricKind::Histogram));
+        if let Some(base) = bucket_base {
+            let (labels, le) = split_label(&metric.labels, "le");
+            if let Some(le) = le.and_then(|v| parse_bound(&v)) {
+                let acc = histograms
+                    .entry((base.to_owned(), label_key(&labels)))
+                    .or_insert_with(|| HistogramAcc {
+                        labels,
+                        ..Default::default()
+                    });
+                acc.buckets.push((le, metric.value));
+            }
+            continue;
+        }
+
+        if let Some(base) = metric.name.strip_suffix("_sum") {
+            match current_kind.get(base) {
+                Some(MetricKind::Histogram) => {
+                    let acc = histograms
+                        .entry((base.to_owned(), label_key(&metric.labels)))
+                        .or_insert_with(|| HistogramAcc {
+                            labels: metric.labels.clone(),
+                            ..Default::default()
+                        });
+                    acc.sum = metric.value;
+                    continue;
+                }
+                Some(MetricKind::Summary) => {
+                    let acc = summaries
+                        .entry((base.to_owned(), label_key(&metric.labels)))
+                        .or_insert_with(|| SummaryAcc {
+                            labels: metric.labels.clone(),
+                            ..Default::default()
+                        });
+                    acc.sum = metric.value;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(base) = metric.name.strip_suffix("_count") {
+            match current_kind.get(base) {
+                Some(MetricKind::Histogram) => {
+                    let acc = histograms
+                        .entry((base.to_owned(), label_key(&metric.labels)))
+                        .or_insert_with(|| HistogramAcc {
+                            labels: metric.labels.clone(),
+                            ..Default::default()
+                        });
+                    acc.count = metric.value;
+                    continue;
+                }
+                Some(MetricKind::Summary) => {
+                    let acc = summaries
+                        .entry((base.to_owned(), label_key(&metric.labels)))
+                        .or_insert_with(|| SummaryAcc {
+                            labels: metric.labels.clone(),
+                            ..Default::default()
+                        });
+                    acc.count = metric.value;
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        if current_kind.get(&metric.name) == Some(&MetricKind::Summary) && metric.labels.contains_key("quantile") {
+            let (labels, quantile) = split_label(&metric.labels, "quantile");
+            if let Some(quantile) = quantile.and_then(|v| parse_bound(&v)) {
+                let acc = summaries
+                    .entry((metric.name.clone(), label_key(&labels)))
+                    .or_insert_with(|| SummaryAcc {
+                        labels,
+                        ..Default::default()
+                    });
+                acc.quantiles.push((quantile, metric.value));
+                continue;
+            }
+        }
+
+        passthrough.push(match current_kind.get(&metric.name) {
+            Some(MetricKind::Counter) => GroupKind::Counter(metric),
+            Some(MetricKind::Gauge) => GroupKind::Gauge(metric),
+            _ => GroupKind::Untyped(metric),
+        });
+    }
+
+    let mut result = passthrough;
+    result.extend(
+        histograms
+            .into_iter()
+            .map(|((name, _), acc)| GroupKind::Histogram(acc.finish(name))),
+    );
+    result.extend(
+        summaries
+            .into_iter()
+            .map(|((name, _), acc)| GroupKind::Summary(acc.finish(name))),
+    );
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Line;
+
+    fn parse_lines(input: &str) -> Vec<Line> {
+        input
+            .lines()
+            .filter_map(|line| Line::parse(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_aggregate_histogram() {
+        let lines = parse_lines(
+            r#"
+            # TYPE http_request_duration_seconds histogram
+            http_request_duration_seconds_bucket{le="0.05"} 24054
+            http_request_duration_seconds_bucket{le="0.1"} 33444
+            http_request_duration_seconds_bucket{le="+Inf"} 144320
+            http_request_duration_seconds_sum 53423
+            http_request_duration_seconds_count 144320
+            "#,
+        );
+        let groups = aggregate(lines);
+        assert_eq!(groups.len(), 1);
+        match &groups[0] {
+            GroupKind::Histogram(h) => {
+                assert_eq!(h.name, "http_request_duration_seconds");
+                assert_eq!(
+                    h.buckets,
+                    vec![(0.05, 24054.0), (0.1, 33444.0), (f64::INFINITY, 144320.0)]
+                );
+                assert_eq!(h.sum, 53423.0);
+                assert_eq!(h.count, 144320.0);
+            }
+            other => panic!("expected histogram, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_summary() {
+        let lines = parse_lines(
+            r#"
+            # TYPE rpc_duration_seconds summary
+            rpc_duration_seconds{quantile="0.01"} 3102
+            rpc_duration_seconds{quantile="0.5"} 4773
+            rpc_duration_seconds_sum 1.7560473e+07
+            rpc_duration_seconds_count 2693
+            "#,
+        );
+        let groups = aggregate(lines);
+        assert_eq!(groups.len(), 1);
+        match &groups[0] {
+            GroupKind::Summary(s) => {
+                assert_eq!(s.name, "rpc_duration_seconds");
+                assert_eq!(s.quantiles, vec![(0.01, 3102.0), (0.5, 4773.0)]);
+                assert_eq!(s.sum, 1.7560473e+07);
+                assert_eq!(s.count, 2693.0);
+            }
+            other => panic!("expected summary, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_counter_passthrough() {
+        let lines = parse_lines(
+            r#"
+            # TYPE http_requests_total counter
+            http_requests_total{method="post",code="200"} 1027
+            "#,
+        );
+        let groups = aggregate(lines);
+        assert_eq!(groups.len(), 1);
+        assert!(matches!(&groups[0], GroupKind::Counter(m) if m.value == 1027.0));
+    }
+
+    #[test]
+    fn test_aggregate_untyped_default() {
+        let lines = parse_lines("metric_without_type 12.47\n");
+        let groups = aggregate(lines);
+        assert_eq!(groups.len(), 1);
+        assert!(matches!(&groups[0], GroupKind::Untyped(m) if m.value == 12.47));
+    }
+}
@@ -0,0 +1,130 @@
+//! Parser for the Prometheus (and OpenMetrics) text exposition format.
+
+mod group;
+mod line;
+
+pub use group::{aggregate, GroupKind, Histogram, Summary};
+pub use line::{parse_text, Exemplar, Header, Help, Line, LineError, Metric, MetricKind, Unit};
+
+use nom::error::ErrorKind;
+use std::fmt;
+
+/// Errors that can occur while parsing Prometheus exposition text.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParserError {
+    ExpectedToken {
+        expected: &'static str,
+        input: String,
+    },
+    InvalidMetricKind {
+        input: String,
+    },
+    ParseFloatError {
+        input: String,
+    },
+    ParseNameError {
+        input: String,
+    },
+    Nom {
+        input: String,
+        kind: ErrorKind,
+    },
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParserError::ExpectedToken { expected, input } => {
+                write!(f, "expected {:?}, found {:?}", expected, input)
+            }
+            ParserError::InvalidMetricKind { input } => {
+                write!(f, "invalid metric kind, found {:?}", input)
+            }
+            ParserError::ParseFloatError { input } => {
+                write!(f, "failed to parse float value, found {:?}", input)
+            }
+            ParserError::ParseNameError { input } => {
+                write!(f, "failed to parse metric name, found {:?}", input)
+            }
+            ParserError::Nom { input, kind } => {
+                write!(f, "{:?} error, found {:?}", kind, input)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParserError {}
+
+impl ParserError {
+    /// The input that remained unparsed at the point this error occurred, used to compute a
+    /// line/column position for document-level diagnostics (see [`crate::parse_text`]) and,
+    /// below, to pick the more specific of two candidate `alt` branch errors.
+    pub(crate) fn remaining_input(&self) -> &str {
+        match self {
+            ParserError::ExpectedToken { input, .. }
+            | ParserError::InvalidMetricKind { input }
+            | ParserError::ParseFloatError { input }
+            | ParserError::ParseNameError { input }
+            | ParserError::Nom { input, .. } => input,
+        }
+    }
+}
+
+impl From<ParserError> for nom::Err<ParserError> {
+    fn from(err: ParserError) -> Self {
+        nom::Err::Error(err)
+    }
+}
+
+impl From<nom::Err<ParserError>> for ParserError {
+    fn from(err: nom::Err<ParserError>) -> Self {
+        match err {
+            nom::Err::Error(e) | nom::Err::Failure(e) => e,
+            nom::Err::Incomplete(_) => ParserError::Nom {
+                input: String::new(),
+                kind: ErrorKind::Complete,
+            },
+        }
+    }
+}
+
+impl<'a> From<nom::Err<(&'a str, ErrorKind)>> for ParserError {
+    fn from(err: nom::Err<(&'a str, ErrorKind)>) -> Self {
+        match err {
+            nom::Err::Error((input, kind)) | nom::Err::Failure((input, kind)) => ParserError::Nom {
+                input: input.to_owned(),
+                kind,
+            },
+            nom::Err::Incomplete(_) => ParserError::Nom {
+                input: String::new(),
+                kind: ErrorKind::Complete,
+            },
+        }
+    }
+}
+
+impl<'a> nom::error::ParseError<&'a str> for ParserError {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        ParserError::Nom {
+            input: input.to_owned(),
+            kind,
+        }
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// `alt` calls this to combine the errors of two failed branches; prefer whichever made
+    /// more progress (left less input unparsed), since that's the more likely root cause.
+    fn or(self, other: Self) -> Self {
+        if other.remaining_input().len() < self.remaining_input().len() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Result type shared by every parser in this crate.
+pub type IResult<I, O> = nom::IResult<I, O, ParserError>;
@@ -5,13 +5,14 @@ use nom::{
     branch::alt,
     bytes::complete::{is_not, tag, take_while, take_while1},
     character::complete::char,
-    combinator::{map, opt, value},
+    combinator::{map, map_res, opt, peek, recognize, value},
     error::ParseError,
     multi::{fold_many0, separated_list},
     number::complete::double,
     sequence::{delimited, pair, preceded, tuple},
 };
 use std::collections::BTreeMap;
+use std::fmt;
 
 type NomError<'a> = nom::Err<(&'a str, nom::error::ErrorKind)>;
 
@@ -35,6 +36,17 @@ pub struct Metric {
     pub name: String,
     pub labels: BTreeMap<String, String>,
     pub value: f64,
+    pub timestamp: Option<i64>,
+    pub exemplar: Option<Exemplar>,
+}
+
+/// An OpenMetrics exemplar: a labeled sample (typically a trace ID) attached to a counter or
+/// histogram bucket, used to correlate an aggregate measurement back to an individual event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exemplar {
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+    pub timestamp: Option<f64>,
 }
 
 impl Metric {
@@ -43,25 +55,66 @@ impl Metric {
     /// ``` text
     /// metric_name [
     ///   "{" label_name "=" `"` label_value `"` { "," label_name "=" `"` label_value `"` } [ "," ] "}"
-    /// ] value [ timestamp ]
+    /// ] value [ timestamp ] [ "#" "{" ... "}" exemplar_value [ exemplar_timestamp ] ]
     /// ```
     ///
-    /// We don't parse timestamp.
+    /// `timestamp` is milliseconds since the Unix epoch, as emitted by scrapers that care
+    /// about event-time rather than ingestion-time semantics. The trailing `#`-introduced
+    /// block is an OpenMetrics exemplar, only recognized when it is followed by `{`.
     fn parse(input: &str) -> IResult<&str, Self> {
         let input = trim_space(input);
         let (input, name) = parse_name(input)?;
         let (input, labels) = Self::parse_labels(input)?;
         let (input, value) = Self::parse_value(input)?;
+        let (input, timestamp) = Self::parse_timestamp(input)?;
+        let (input, exemplar) = Self::parse_exemplar(input)?;
         Ok((
             input,
             Metric {
                 name,
                 labels,
                 value,
+                timestamp,
+                exemplar,
             },
         ))
     }
 
+    /// Optional OpenMetrics exemplar: `"#" "{" label_name "=" label_value, ... "}" value [ timestamp ]`.
+    /// Only attempted when the `#` is immediately (modulo whitespace) followed by `{`, so a
+    /// plain trailing comment is left alone.
+    fn parse_exemplar(input: &str) -> IResult<&str, Option<Exemplar>> {
+        opt(preceded(
+            tuple((sp, char('#'), sp, peek(char('{')))),
+            map(
+                tuple((
+                    Self::parse_labels,
+                    Self::parse_value,
+                    opt(preceded(sp, Self::parse_value)),
+                )),
+                |(labels, value, timestamp)| Exemplar {
+                    labels,
+                    value,
+                    timestamp,
+                },
+            ),
+        ))(input)
+    }
+
+    /// Optional `timestamp`, milliseconds since the Unix epoch, as a signed integer.
+    fn parse_timestamp(input: &str) -> IResult<&str, Option<i64>> {
+        opt(preceded(
+            sp,
+            map_res(
+                recognize(pair(
+                    opt(alt((char('+'), char('-')))),
+                    take_while1(|c: char| c.is_ascii_digit()),
+                )),
+                |s: &str| s.parse::<i64>(),
+            ),
+        ))(input)
+    }
+
     /// Float value, and +Inf, -Int, Nan.
     pub fn parse_value(input: &str) -> IResult<&str, f64> {
         let input = trim_space(input);
@@ -198,11 +251,108 @@ impl Header {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Help {
+    pub metric_name: String,
+    pub text: String,
+}
+
+impl Help {
+    /// `# HELP <metric_name> <text>`
+    fn parse(input: &str) -> IResult<&str, Self> {
+        let input = trim_space(input);
+        let (input, _) = tag("#")(input).map_err(|_: NomError| ParserError::ExpectedToken {
+            expected: "#",
+            input: input.to_owned(),
+        })?;
+        let input = trim_space(input);
+        let (input, _) = tag("HELP")(input).map_err(|_: NomError| ParserError::ExpectedToken {
+            expected: "HELP",
+            input: input.to_owned(),
+        })?;
+        let (input, metric_name) = parse_name(input)?;
+        let input = trim_space(input);
+        let (input, text) = parse_escaped_text(input)?;
+        Ok((input, Help { metric_name, text }))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub metric_name: String,
+    pub unit: String,
+}
+
+impl Unit {
+    /// `# UNIT <metric_name> <unit>`
+    fn parse(input: &str) -> IResult<&str, Self> {
+        let input = trim_space(input);
+        let (input, _) = tag("#")(input).map_err(|_: NomError| ParserError::ExpectedToken {
+            expected: "#",
+            input: input.to_owned(),
+        })?;
+        let input = trim_space(input);
+        let (input, _) = tag("UNIT")(input).map_err(|_: NomError| ParserError::ExpectedToken {
+            expected: "UNIT",
+            input: input.to_owned(),
+        })?;
+        let (input, metric_name) = parse_name(input)?;
+        let input = trim_space(input);
+        let (input, unit) =
+            take_while1(|c: char| !c.is_whitespace())(input).map_err(|_: NomError| {
+                ParserError::ExpectedToken {
+                    expected: "unit",
+                    input: input.to_owned(),
+                }
+            })?;
+        Ok((
+            input,
+            Unit {
+                metric_name,
+                unit: unit.to_owned(),
+            },
+        ))
+    }
+}
+
+/// Parse the free-form text following `# HELP <metric_name>` to the end of the line,
+/// unescaping `\\` and `\n` the same way label values are (but without surrounding quotes).
+/// A backslash that starts neither escape is kept as is.
+fn parse_escaped_text(input: &str) -> IResult<&str, String> {
+    #[derive(Debug)]
+    enum StringFragment<'a> {
+        Literal(&'a str),
+        EscapedChar(char),
+    }
+
+    let parse_string_fragment = alt((
+        map(is_not("\\"), StringFragment::Literal),
+        map(
+            preceded(
+                char('\\'),
+                alt((value('\n', char('n')), value('\\', char('\\')))),
+            ),
+            StringFragment::EscapedChar,
+        ),
+        map(tag("\\"), StringFragment::Literal),
+    ));
+
+    fold_many0(parse_string_fragment, String::new(), |mut result, fragment| {
+        match fragment {
+            StringFragment::Literal(s) => result.push_str(s),
+            StringFragment::EscapedChar(c) => result.push(c),
+        }
+        result
+    })(input)
+}
+
 /// Each line of Prometheus text format.
-/// We discard empty lines, comments, and timestamps.
+/// We discard empty lines and unrecognized comments.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Line {
     Header(Header),
+    Help(Help),
+    Unit(Unit),
     Metric(Metric),
 }
 
@@ -216,6 +366,8 @@ impl Line {
         alt((
             map(Metric::parse, |r| Some(Line::Metric(r))),
             map(Header::parse, |r| Some(Line::Header(r))),
+            map(Help::parse, |r| Some(Line::Help(r))),
+            map(Unit::parse, |r| Some(Line::Unit(r))),
             value(None, char('#')),
         ))(input)
     }
@@ -228,6 +380,180 @@ impl Line {
     }
 }
 
+/// A [`ParserError`] anchored to where it occurred within a multi-line exposition document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineError {
+    /// 1-indexed line number within the document.
+    pub line: usize,
+    /// 1-indexed column within that line.
+    pub column: usize,
+    pub error: ParserError,
+}
+
+/// Parse a full exposition document one line at a time. Unlike [`Line::parse`], a malformed
+/// line does not abort the document: its position and error are collected into `errors` and
+/// the remaining lines are still parsed into `lines`.
+pub fn parse_text(input: &str) -> (Vec<Line>, Vec<LineError>) {
+    let mut lines = Vec::new();
+    let mut errors = Vec::new();
+    for (number, text) in input.lines().enumerate() {
+        match Line::parse_inner(text) {
+            Ok((_, Some(line))) => lines.push(line),
+            Ok((_, None)) => {}
+            Err(err) => errors.push(LineError {
+                line: number + 1,
+                column: error_column(text, &err),
+                error: err.into(),
+            }),
+        }
+    }
+    (lines, errors)
+}
+
+/// The 1-indexed column at which `err` occurred within `line`, derived from the
+/// remaining-input offset nom already tracks on every parser error.
+///
+/// `line` is the raw, untrimmed line, but [`Line::parse_inner`] parses `line.trim()` (which
+/// strips leading *and* trailing whitespace), so the column is computed within the trimmed
+/// text and then shifted by the length of the stripped leading whitespace.
+fn error_column(line: &str, err: &nom::Err<ParserError>) -> usize {
+    let remaining = match err {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.remaining_input(),
+        nom::Err::Incomplete(_) => "",
+    };
+    let leading_whitespace = line.len() - line.trim_start().len();
+    let trimmed_len = line.trim().len();
+    leading_whitespace + trimmed_len - remaining.len().min(trimmed_len) + 1
+}
+
+impl fmt::Display for Header {
+    /// Render as `# TYPE <metric_name> <kind>`, the inverse of [`Header::parse`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "# TYPE {} {}", self.metric_name, self.kind.as_str())
+    }
+}
+
+impl MetricKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+            MetricKind::Histogram => "histogram",
+            MetricKind::Summary => "summary",
+            MetricKind::Untyped => "untyped",
+        }
+    }
+}
+
+impl fmt::Display for Help {
+    /// Render as `# HELP <metric_name> <text>`, the inverse of [`Help::parse`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "# HELP {} {}", self.metric_name, escape_text(&self.text))
+    }
+}
+
+impl fmt::Display for Unit {
+    /// Render as `# UNIT <metric_name> <unit>`, the inverse of [`Unit::parse`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "# UNIT {} {}", self.metric_name, self.unit)
+    }
+}
+
+impl fmt::Display for Metric {
+    /// Render back to Prometheus/OpenMetrics exposition text, the inverse of [`Metric::parse`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        write_labels(f, &self.labels)?;
+        write!(f, " ")?;
+        write_value(f, self.value)?;
+        if let Some(timestamp) = self.timestamp {
+            write!(f, " {}", timestamp)?;
+        }
+        if let Some(exemplar) = &self.exemplar {
+            write!(f, " # ")?;
+            write_labels(f, &exemplar.labels)?;
+            write!(f, " ")?;
+            write_value(f, exemplar.value)?;
+            if let Some(timestamp) = exemplar.timestamp {
+                write!(f, " {}", timestamp)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Line {
+    /// Render back to Prometheus/OpenMetrics exposition text, the inverse of [`Line::parse`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Line::Header(header) => write!(f, "{}", header),
+            Line::Help(help) => write!(f, "{}", help),
+            Line::Unit(unit) => write!(f, "{}", unit),
+            Line::Metric(metric) => write!(f, "{}", metric),
+        }
+    }
+}
+
+/// Write `value`, rendering non-finite floats as `+Inf`/`-Inf`/`NaN` the way the text format
+/// expects. [`Metric::parse_value`] reads back `+Inf` and `-Inf`, but recognizes NaN only when
+/// it is spelled `Nan`, so a `NaN` written here does not parse back.
+fn write_value(f: &mut fmt::Formatter<'_>, value: f64) -> fmt::Result {
+    if value.is_nan() {
+        write!(f, "NaN")
+    } else if value == f64::INFINITY {
+        write!(f, "+Inf")
+    } else if value == f64::NEG_INFINITY {
+        write!(f, "-Inf")
+    } else {
+        write!(f, "{}", value)
+    }
+}
+
+/// Write `{label="value",...}`, sorted by label name (the `BTreeMap` already gives that
+/// order), or nothing at all when there are no labels. The inverse of [`Metric::parse_labels`].
+fn write_labels(f: &mut fmt::Formatter<'_>, labels: &BTreeMap<String, String>) -> fmt::Result {
+    if labels.is_empty() {
+        return Ok(());
+    }
+    write!(f, "{{")?;
+    for (i, (name, value)) in labels.iter().enumerate() {
+        if i > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}=\"{}\"", name, escape_label_value(value))?;
+    }
+    write!(f, "}}")
+}
+
+/// Escape `\`, `"`, and newline the way a quoted label value requires, the inverse of
+/// [`Metric::parse_escaped_string`].
+fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape `\` and newline in unquoted free-form text (e.g. `HELP`), the inverse of
+/// [`parse_escaped_text`].
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// Name matches the regex `[a-zA-Z_][a-zA-Z0-9_]*`.
 fn parse_name(input: &str) -> IResult<&str, String> {
     let input = trim_space(input);
@@ -390,6 +716,51 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_parse_help() {
+        // HELP text runs to the end of the line, so (unlike other parsers) it leaves no tail.
+        let input = "  \t #  HELP abc_def some help text";
+        let (left, r) = Help::parse(input).unwrap();
+        assert_eq!(left, "");
+        assert_eq!(
+            r,
+            Help {
+                metric_name: "abc_def".into(),
+                text: "some help text".into(),
+            }
+        );
+
+        let input = r#"# HELP abc_def escaped \\ and \n here"#;
+        let (left, r) = Help::parse(input).unwrap();
+        assert_eq!(left, "");
+        assert_eq!(
+            r,
+            Help {
+                metric_name: "abc_def".into(),
+                text: "escaped \\ and \n here".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_unit() {
+        fn wrap(s: &str) -> String {
+            format!("  \t {}  .", s)
+        }
+        let tail = "  .";
+
+        let input = wrap("#  UNIT abc_def_seconds seconds");
+        let (left, r) = Unit::parse(&input).unwrap();
+        assert_eq!(left, tail);
+        assert_eq!(
+            r,
+            Unit {
+                metric_name: "abc_def_seconds".into(),
+                unit: "seconds".into(),
+            }
+        );
+    }
+
     #[test]
     fn test_parse_value() {
         fn wrap(s: &str) -> String {
@@ -429,6 +800,144 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_parse_timestamp() {
+        fn wrap(s: &str) -> String {
+            format!("  \t {}  .", s)
+        }
+        let tail = "  .";
+
+        let input = wrap("1395066363000");
+        let (left, r) = Metric::parse_timestamp(&input).unwrap();
+        assert_eq!(left, tail);
+        assert_eq!(r, Some(1395066363000));
+
+        let input = wrap("-3982045");
+        let (left, r) = Metric::parse_timestamp(&input).unwrap();
+        assert_eq!(left, tail);
+        assert_eq!(r, Some(-3982045));
+
+        let input = "  \t  .";
+        let (left, r) = Metric::parse_timestamp(input).unwrap();
+        assert_eq!(left, input);
+        assert_eq!(r, None);
+
+        // A digit string that overflows i64 must not be silently coerced to a sentinel value;
+        // `opt` backtracks it to a parse failure, same as no timestamp being present.
+        let input = wrap("99999999999999999999");
+        let (left, r) = Metric::parse_timestamp(&input).unwrap();
+        assert_eq!(left, input);
+        assert_eq!(r, None);
+    }
+
+    #[test]
+    fn test_parse_exemplar() {
+        fn wrap(s: &str) -> String {
+            format!("  \t {}  .", s)
+        }
+        let tail = "  .";
+
+        let input = wrap(r#"# {trace_id="abc123"} 1 1395066363"#);
+        let (left, r) = Metric::parse_exemplar(&input).unwrap();
+        assert_eq!(left, tail);
+        assert_eq!(
+            r,
+            Some(Exemplar {
+                labels: map! {"trace_id" => "abc123"},
+                value: 1.0,
+                timestamp: Some(1395066363.0),
+            })
+        );
+
+        let input = wrap(r#"# {trace_id="abc123"} 1"#);
+        let (left, r) = Metric::parse_exemplar(&input).unwrap();
+        assert_eq!(left, tail);
+        assert_eq!(
+            r,
+            Some(Exemplar {
+                labels: map! {"trace_id" => "abc123"},
+                value: 1.0,
+                timestamp: None,
+            })
+        );
+
+        // A plain comment (no `{` immediately after `#`) is left untouched.
+        let input = wrap("# just a comment");
+        let (left, r) = Metric::parse_exemplar(&input).unwrap();
+        assert_eq!(left, input);
+        assert_eq!(r, None);
+    }
+
+    #[test]
+    fn test_display_metric() {
+        let metric = Metric {
+            name: "http_requests_total".into(),
+            labels: map! {"method" => "post", "code" => "200"},
+            value: 1027.0,
+            timestamp: Some(1395066363000),
+            exemplar: None,
+        };
+        assert_eq!(
+            metric.to_string(),
+            r#"http_requests_total{code="200",method="post"} 1027 1395066363000"#
+        );
+
+        let metric = Metric {
+            name: "msdos_file_access_time_seconds".into(),
+            labels: map! {"path" => "C:\\DIR\\FILE.TXT", "error" => "Cannot find file:\n\"FILE.TXT\""},
+            value: f64::INFINITY,
+            timestamp: None,
+            exemplar: None,
+        };
+        assert_eq!(
+            metric.to_string(),
+            r#"msdos_file_access_time_seconds{error="Cannot find file:\n\"FILE.TXT\"",path="C:\\DIR\\FILE.TXT"} +Inf"#
+        );
+
+        let metric = Metric {
+            name: "http_requests_total".into(),
+            labels: BTreeMap::new(),
+            value: 1.0,
+            timestamp: None,
+            exemplar: Some(Exemplar {
+                labels: map! {"trace_id" => "abc123"},
+                value: 1.0,
+                timestamp: Some(1395066363.0),
+            }),
+        };
+        assert_eq!(
+            metric.to_string(),
+            r#"http_requests_total 1 # {trace_id="abc123"} 1 1395066363"#
+        );
+    }
+
+    #[test]
+    fn test_display_header_help_unit() {
+        let header = Header {
+            metric_name: "http_requests_total".into(),
+            kind: MetricKind::Counter,
+        };
+        assert_eq!(header.to_string(), "# TYPE http_requests_total counter");
+
+        let help = Help {
+            metric_name: "http_requests_total".into(),
+            text: "The total number of HTTP requests.".into(),
+        };
+        assert_eq!(
+            help.to_string(),
+            "# HELP http_requests_total The total number of HTTP requests."
+        );
+
+        let unit = Unit {
+            metric_name: "http_request_duration_seconds".into(),
+            unit: "seconds".into(),
+        };
+        assert_eq!(
+            unit.to_string(),
+            "# UNIT http_request_duration_seconds seconds"
+        );
+    }
+
     #[test]
     fn test_parse_labels() {
         fn wrap(s: &str) -> String {
@@ -498,6 +1007,7 @@ mod test {
 
             # A histogram, which has a pretty complex representation in the text format:
             # HELP http_request_duration_seconds A histogram of the request duration.
+            # UNIT http_request_duration_seconds seconds
             # TYPE http_request_duration_seconds histogram
             http_request_duration_seconds_bucket{le="0.05"} 24054
             http_request_duration_seconds_bucket{le="0.1"} 33444
@@ -521,4 +1031,35 @@ mod test {
             "##;
         assert!(input.lines().map(Line::parse).all(|r| r.is_ok()));
     }
+
+    #[test]
+    fn test_parse_text() {
+        let lines = [
+            "# TYPE http_requests_total counter",
+            "http_requests_total{method=\"post\"} 1",
+            "not a valid line at all ===",
+            "http_requests_total{method=\"get\"} 2",
+        ];
+        let input = lines.join("\n") + "\n";
+        let (parsed, errors) = parse_text(&input);
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+        // "not" parses as a metric name, so the most specific error is the failed value parse
+        // at "a valid line...", which starts at column 5.
+        assert_eq!(errors[0].column, 5);
+    }
+
+    #[test]
+    fn test_parse_text_trailing_whitespace_does_not_shift_column() {
+        // The error is at column 1 of "@@@", but the raw line has trailing whitespace that
+        // `Line::parse_inner`'s `input.trim()` strips before parsing; that must not be counted
+        // towards the reported column.
+        let input = "@@@ \n";
+        let (parsed, errors) = parse_text(input);
+        assert!(parsed.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 1);
+        assert_eq!(errors[0].column, 1);
+    }
 }
\ No newline at end of file